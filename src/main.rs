@@ -1,31 +1,100 @@
+mod output;
+mod server;
+mod snapshot;
+
+use output::Format;
+
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::{Connection, OpenFlags};
 use std::env;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::process;
+use std::thread;
 
-const DEFAULT_DB_PATH: &str = "btc_addresses.db";
+pub(crate) const DEFAULT_DB_PATH: &str = "btc_addresses.db";
 const DEFAULT_TXT_PATH: &str = "addressonly.txt";
 const BATCH_SIZE: usize = 1000;
 
+// Shared with the pooled connections built for --threads and `serve` so
+// every worker sees the same read-only / WAL tuning as the single-connection
+// path.
+pub(crate) const PRAGMAS: &str = "PRAGMA journal_mode = WAL;
+         PRAGMA synchronous = NORMAL;
+         PRAGMA temp_store = MEMORY;
+         PRAGMA mmap_size = 268435456;
+         PRAGMA cache_size = -64000;
+         PRAGMA query_only = ON;
+         PRAGMA locking_mode = NORMAL;
+         PRAGMA read_uncommitted = 1;";
+
+const CSV_JOIN_PRAGMAS: &str = "PRAGMA journal_mode = WAL;
+         PRAGMA synchronous = NORMAL;
+         PRAGMA temp_store = MEMORY;
+         PRAGMA mmap_size = 268435456;
+         PRAGMA cache_size = -64000;
+         PRAGMA locking_mode = NORMAL;
+         PRAGMA read_uncommitted = 1;";
+
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    
-    let db_path = args.get(1).map(|s| s.as_str()).unwrap_or(DEFAULT_DB_PATH);
-    let txt_path = args.get(2).map(|s| s.as_str()).unwrap_or(DEFAULT_TXT_PATH);
-    
-    match check_addresses(db_path, txt_path) {
+    let raw_args: Vec<String> = env::args().skip(1).collect();
+
+    if raw_args.first().map(|s| s.as_str()) == Some("serve") {
+        if let Err(e) = server::run(&raw_args[1..]) {
+            eprintln!("Error: {}", e);
+            process::exit(2);
+        }
+        return;
+    }
+
+    if raw_args.first().map(|s| s.as_str()) == Some("snapshot") {
+        if let Err(e) = snapshot::run(&raw_args[1..]) {
+            eprintln!("Error: {}", e);
+            process::exit(2);
+        }
+        return;
+    }
+
+    let (positional, threads, format, normalize) = parse_args(&raw_args);
+
+    let db_path = positional.first().map(|s| s.as_str()).unwrap_or(DEFAULT_DB_PATH);
+    let txt_path = positional.get(1).map(|s| s.as_str()).unwrap_or(DEFAULT_TXT_PATH);
+
+    let result = match threads {
+        Some(1) | None => check_addresses(db_path, txt_path, normalize),
+        Some(n) => check_addresses_parallel(db_path, txt_path, n, normalize),
+    };
+
+    match result {
         Ok(found_addresses) => {
-            if !found_addresses.is_empty() {
-                eprintln!("✓ Found {} address(es) in database:", found_addresses.len());
-                for addr in &found_addresses {
-                    eprintln!("  → {}", addr);
+            if format == Format::Text {
+                if !found_addresses.is_empty() {
+                    eprintln!("✓ Found {} address(es) in database:", found_addresses.len());
+                    for addr in &found_addresses {
+                        eprintln!("  → {}", addr);
+                    }
+                } else {
+                    eprintln!("✗ No addresses found in database");
                 }
-                process::exit(1);
             } else {
-                eprintln!("✗ No addresses found in database");
-                process::exit(0);
+                // The not-found report needs the full input list, which the
+                // csv-vtab fast path deliberately avoids loading otherwise.
+                match read_addresses(txt_path) {
+                    Ok(addresses) => {
+                        if let Err(e) = output::write_structured(format, &addresses, &found_addresses, db_path, txt_path, normalize) {
+                            eprintln!("Error: {}", e);
+                            process::exit(2);
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        process::exit(2);
+                    }
+                }
             }
+
+            process::exit(if found_addresses.is_empty() { 0 } else { 1 });
         }
         Err(e) => {
             eprintln!("Error: {}", e);
@@ -34,7 +103,65 @@ fn main() {
     }
 }
 
-fn check_addresses(db_path: &str, txt_path: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+// Splits `--threads N`, `--format {text,json,ndjson}` and `--normalize` out
+// of the positional db/txt arguments. Returns the remaining positional
+// args, the thread count (None means "sequential", matching the tool's
+// original behavior), the output format (defaults to `Format::Text`), and
+// whether normalized matching is enabled (defaults to off, since it forces
+// a full scan unless the DB has a matching expression index).
+fn parse_args(args: &[String]) -> (Vec<String>, Option<usize>, Format, bool) {
+    let mut positional = Vec::new();
+    let mut threads = None;
+    let mut format = Format::Text;
+    let mut normalize = false;
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--threads" => {
+                if let Some(value) = iter.next() {
+                    threads = value.parse().ok();
+                }
+            }
+            "--format" => {
+                if let Some(value) = iter.next() {
+                    format = Format::parse(value).unwrap_or(Format::Text);
+                }
+            }
+            "--normalize" => normalize = true,
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    (positional, threads, format, normalize)
+}
+
+pub(crate) fn default_thread_count() -> usize {
+    thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+}
+
+// Tries the single-statement CSV virtual table JOIN first, since it lets
+// SQLite's query planner use the index on `addresses.address` for the whole
+// file without the 1000-placeholder IN-list limit. Falls back to the
+// batched IN-list path when the vtab can't be created (e.g. the input file
+// can't be memory-mapped by the csv module). `--normalize` always goes
+// straight to the batched path, since it needs `normalize_addr()` applied
+// per candidate rather than a plain equi-join.
+fn check_addresses(db_path: &str, txt_path: &str, normalize: bool) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    if normalize {
+        return check_addresses_batched(db_path, txt_path, true);
+    }
+
+    match check_addresses_via_csv_join(db_path, txt_path) {
+        Ok(found) => Ok(found),
+        Err(e) => {
+            eprintln!("csv vtab join unavailable ({}), falling back to batched IN-list scan", e);
+            check_addresses_batched(db_path, txt_path, false)
+        }
+    }
+}
+
+fn check_addresses_via_csv_join(db_path: &str, txt_path: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
     // Open database in read-only mode with optimizations
     let conn = Connection::open_with_flags(
         db_path,
@@ -44,68 +171,395 @@ fn check_addresses(db_path: &str, txt_path: &str) -> Result<Vec<String>, Box<dyn
     // Set busy timeout to handle disk I/O contention (5 seconds)
     conn.busy_timeout(std::time::Duration::from_secs(5))?;
 
-    // Set SQLite optimizations for read-only access with WAL support
-    conn.execute_batch(
-        "PRAGMA journal_mode = WAL;
-         PRAGMA synchronous = NORMAL;
-         PRAGMA temp_store = MEMORY;
-         PRAGMA mmap_size = 268435456;
-         PRAGMA cache_size = -64000;
-         PRAGMA query_only = ON;
-         PRAGMA locking_mode = NORMAL;
-         PRAGMA read_uncommitted = 1;"
+    // Same tuning as `PRAGMAS`, minus `query_only`: creating the csv vtab
+    // below writes to the temp database, which `query_only = ON` blocks too
+    // even though the `addresses` table is already protected by
+    // `SQLITE_OPEN_READ_ONLY`.
+    conn.execute_batch(CSV_JOIN_PRAGMAS)?;
+
+    rusqlite::vtab::csvtab::load_module(&conn)?;
+
+    conn.execute_batch(&format!(
+        "CREATE VIRTUAL TABLE temp.candidates USING csv(filename={}, header=no)",
+        escape_sql_string_literal(txt_path)
+    ))?;
+
+    // DISTINCT matches the batched IN-list fallback's semantics: a row
+    // matching N duplicate lines in the input file is still reported once.
+    // TRIM(c.c0) matches `read_addresses`, which every other path runs
+    // through: the csv vtab hands back each field verbatim, so without this
+    // whitespace-padded input lines would silently stop matching here while
+    // still matching via the batched fallback.
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT a.address FROM addresses a JOIN temp.candidates c ON a.address = TRIM(c.c0)",
     )?;
-    
-    // Read addresses from file
-    let file = File::open(txt_path)?;
-    let reader = BufReader::with_capacity(1024 * 1024, file); // 1MB buffer
-    
-    let mut addresses = Vec::new();
-    for line in reader.lines() {
-        let address = line?.trim().to_string();
-        if !address.is_empty() {
-            addresses.push(address);
-        }
-    }
-    
+
+    let found = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+
+    Ok(found)
+}
+
+// Single-quoted SQL string literal, doubling embedded single quotes. Used
+// for the vtab's `filename=` module argument, which rusqlite can't bind as
+// a normal parameter.
+fn escape_sql_string_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+fn check_addresses_batched(db_path: &str, txt_path: &str, normalize: bool) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    // Open database in read-only mode with optimizations
+    let conn = Connection::open_with_flags(
+        db_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+
+    // Set busy timeout to handle disk I/O contention (5 seconds)
+    conn.busy_timeout(std::time::Duration::from_secs(5))?;
+
+    // Set SQLite optimizations for read-only access with WAL support
+    conn.execute_batch(PRAGMAS)?;
+    register_normalize_function(&conn)?;
+
+    let addresses = read_addresses(txt_path)?;
     if addresses.is_empty() {
         return Ok(Vec::new());
     }
-    
+
     let mut found_addresses = Vec::new();
-    
+
     // Process in batches for better performance
     for chunk in addresses.chunks(BATCH_SIZE) {
-        let mut batch_found = check_batch(&conn, chunk)?;
+        let mut batch_found = check_batch(&conn, chunk, normalize)?;
         found_addresses.append(&mut batch_found);
     }
-    
+
+    Ok(found_addresses)
+}
+
+// Pooled, multi-threaded counterpart to `check_addresses`. SQLite read-only
+// connections over WAL support concurrent readers, so each worker gets its
+// own pooled connection and scans a contiguous slice of `addresses`; slices
+// are processed in thread order so results stay in input order.
+fn check_addresses_parallel(
+    db_path: &str,
+    txt_path: &str,
+    threads: usize,
+    normalize: bool,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let threads = if threads == 0 { default_thread_count() } else { threads };
+
+    let addresses = read_addresses(txt_path)?;
+    if addresses.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let pool = build_pool(db_path, threads as u32)?;
+    let chunk_size = addresses.len().div_ceil(threads).max(1);
+
+    // `Box<dyn Error>` isn't `Send`, so worker results cross the `thread::scope`
+    // boundary as `String` (`Send`) and get reboxed into the ordinary error
+    // type once back on the joining thread.
+    let found_addresses = thread::scope(|scope| -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let handles: Vec<_> = addresses
+            .chunks(chunk_size)
+            .map(|slice| {
+                let pool = &pool;
+                scope.spawn(move || -> Result<Vec<String>, String> {
+                    let conn = pool.get().map_err(|e| e.to_string())?;
+                    let mut slice_found = Vec::new();
+                    for batch in slice.chunks(BATCH_SIZE) {
+                        let batch_found = check_batch(&conn, batch, normalize).map_err(|e| e.to_string())?;
+                        slice_found.extend(batch_found);
+                    }
+                    Ok(slice_found)
+                })
+            })
+            .collect();
+
+        // Dedup across chunk boundaries: a duplicate input line can land in
+        // two different chunks, and each chunk reports it independently.
+        let mut seen = std::collections::HashSet::new();
+        let mut found_addresses = Vec::new();
+        for handle in handles {
+            let slice_found = handle.join().expect("worker thread panicked")?;
+            for address in slice_found {
+                if seen.insert(address.clone()) {
+                    found_addresses.push(address);
+                }
+            }
+        }
+        Ok(found_addresses)
+    })?;
+
     Ok(found_addresses)
 }
 
-fn check_batch(conn: &Connection, addresses: &[String]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+pub(crate) fn build_pool(db_path: &str, pool_size: u32) -> Result<Pool<SqliteConnectionManager>, Box<dyn std::error::Error>> {
+    let manager = SqliteConnectionManager::file(db_path)
+        .with_flags(OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+        .with_init(|conn| {
+            conn.busy_timeout(std::time::Duration::from_secs(5))?;
+            conn.execute_batch(PRAGMAS)?;
+            register_normalize_function(conn)?;
+            Ok(())
+        });
+
+    let pool = Pool::builder().max_size(pool_size.max(1)).build(manager)?;
+    Ok(pool)
+}
+
+// Lowercases and trims an address the same way `normalize_addr()` does in
+// SQL, so bound parameters match rows the scalar function would match.
+pub(crate) fn normalize_address(address: &str) -> String {
+    address.trim().to_lowercase()
+}
+
+// Registers `normalize_addr(text)` on `conn` for the `--normalize` matching
+// mode. For this to be fast on large tables it needs a matching expression
+// index on the DB side, e.g.
+// `CREATE INDEX idx_addresses_normalized ON addresses (normalize_addr(address))`;
+// without one, `WHERE normalize_addr(address) IN (...)` is a full scan.
+fn register_normalize_function(conn: &Connection) -> rusqlite::Result<()> {
+    conn.create_scalar_function(
+        "normalize_addr",
+        1,
+        rusqlite::functions::FunctionFlags::SQLITE_UTF8 | rusqlite::functions::FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let address: String = ctx.get(0)?;
+            Ok(normalize_address(&address))
+        },
+    )
+}
+
+fn read_addresses(txt_path: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let file = File::open(txt_path)?;
+    let reader = BufReader::with_capacity(1024 * 1024, file); // 1MB buffer
+
+    let mut addresses = Vec::new();
+    for line in reader.lines() {
+        let address = line?.trim().to_string();
+        if !address.is_empty() {
+            addresses.push(address);
+        }
+    }
+
+    Ok(addresses)
+}
+
+pub(crate) fn check_batch(conn: &Connection, addresses: &[String], normalize: bool) -> Result<Vec<String>, Box<dyn std::error::Error>> {
     // Build parameterized query for batch checking
     let placeholders = vec!["?"; addresses.len()].join(",");
-    let query = format!(
-        "SELECT address FROM addresses WHERE address IN ({})",
-        placeholders
-    );
-    
+    let query = if normalize {
+        format!(
+            "SELECT address FROM addresses WHERE normalize_addr(address) IN ({})",
+            placeholders
+        )
+    } else {
+        format!(
+            "SELECT address FROM addresses WHERE address IN ({})",
+            placeholders
+        )
+    };
+
     let mut stmt = conn.prepare_cached(&query)?;
-    
-    // Convert addresses to rusqlite parameters
+
+    // Convert addresses to rusqlite parameters, normalizing them the same
+    // way `normalize_addr()` does in SQL when that mode is on.
+    let normalized;
+    let addresses: &[String] = if normalize {
+        normalized = addresses.iter().map(|s| normalize_address(s)).collect::<Vec<_>>();
+        &normalized
+    } else {
+        addresses
+    };
     let params: Vec<&dyn rusqlite::ToSql> = addresses
         .iter()
         .map(|s| s as &dyn rusqlite::ToSql)
         .collect();
-    
+
     let mut found = Vec::new();
     let mut rows = stmt.query(&params[..])?;
-    
+
     while let Some(row) = rows.next()? {
         let address: String = row.get(0)?;
         found.push(address);
     }
-    
+
     Ok(found)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!("db_search_test_{}_{}", std::process::id(), name));
+        path
+    }
+
+    fn make_test_db(path: &std::path::Path) {
+        let conn = Connection::open(path).unwrap();
+        // Pre-switch to WAL so the read-only connections under test (which
+        // set `PRAGMA journal_mode = WAL` themselves) don't need write
+        // access just to confirm the mode is already WAL.
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             CREATE TABLE addresses (address TEXT PRIMARY KEY);
+             INSERT INTO addresses (address) VALUES ('addr1'), ('Addr2'), ('addr3');",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn parse_args_splits_flags_from_positional() {
+        let args: Vec<String> =
+            ["db.sqlite", "in.txt", "--threads", "4", "--format", "json", "--normalize"].iter().map(|s| s.to_string()).collect();
+        let (positional, threads, format, normalize) = parse_args(&args);
+        assert_eq!(positional, vec!["db.sqlite".to_string(), "in.txt".to_string()]);
+        assert_eq!(threads, Some(4));
+        assert!(format == Format::Json);
+        assert!(normalize);
+    }
+
+    #[test]
+    fn parse_args_defaults_when_no_flags() {
+        let args: Vec<String> = ["db.sqlite", "in.txt"].iter().map(|s| s.to_string()).collect();
+        let (positional, threads, format, normalize) = parse_args(&args);
+        assert_eq!(positional, vec!["db.sqlite".to_string(), "in.txt".to_string()]);
+        assert_eq!(threads, None);
+        assert!(format == Format::Text);
+        assert!(!normalize);
+    }
+
+    #[test]
+    fn escape_sql_string_literal_doubles_quotes() {
+        assert_eq!(escape_sql_string_literal("plain.txt"), "'plain.txt'");
+        assert_eq!(escape_sql_string_literal("weird'file.txt"), "'weird''file.txt'");
+    }
+
+    #[test]
+    fn normalize_address_trims_and_lowercases() {
+        assert_eq!(normalize_address("  BtcAddr  "), "btcaddr");
+    }
+
+    #[test]
+    fn check_batch_exact_match_is_case_sensitive() {
+        let path = temp_path("exact.db");
+        let _ = std::fs::remove_file(&path);
+        make_test_db(&path);
+        let conn = Connection::open(&path).unwrap();
+
+        // The DB stores "Addr2"; querying lowercase "addr2" must not match
+        // without `--normalize`.
+        let found = check_batch(&conn, &["addr1".to_string(), "addr2".to_string()], false).unwrap();
+        assert_eq!(found, vec!["addr1".to_string()]);
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn check_batch_normalize_matches_case_and_whitespace() {
+        let path = temp_path("normalize.db");
+        let _ = std::fs::remove_file(&path);
+        make_test_db(&path);
+        let conn = Connection::open(&path).unwrap();
+        register_normalize_function(&conn).unwrap();
+
+        let found = check_batch(&conn, &[" addr2 ".to_string()], true).unwrap();
+        assert_eq!(found, vec!["Addr2".to_string()]);
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn csv_join_dedups_repeated_candidates() {
+        let db_path = temp_path("csvjoin.db");
+        let txt_path = temp_path("csvjoin.txt");
+        let _ = std::fs::remove_file(&db_path);
+        let _ = std::fs::remove_file(&txt_path);
+        make_test_db(&db_path);
+
+        let mut file = std::fs::File::create(&txt_path).unwrap();
+        writeln!(file, "addr1").unwrap();
+        writeln!(file, "addr1").unwrap();
+        writeln!(file, "addr1").unwrap();
+        writeln!(file, "addr3").unwrap();
+        drop(file);
+
+        let mut found = check_addresses_via_csv_join(db_path.to_str().unwrap(), txt_path.to_str().unwrap()).unwrap();
+        found.sort();
+        assert_eq!(found, vec!["addr1".to_string(), "addr3".to_string()]);
+
+        std::fs::remove_file(&db_path).ok();
+        std::fs::remove_file(&txt_path).ok();
+    }
+
+    #[test]
+    fn csv_join_trims_whitespace_padded_input() {
+        let db_path = temp_path("csvjoin_trim.db");
+        let txt_path = temp_path("csvjoin_trim.txt");
+        let _ = std::fs::remove_file(&db_path);
+        let _ = std::fs::remove_file(&txt_path);
+        make_test_db(&db_path);
+
+        let mut file = std::fs::File::create(&txt_path).unwrap();
+        writeln!(file, "  addr1  ").unwrap();
+        drop(file);
+
+        let found = check_addresses_via_csv_join(db_path.to_str().unwrap(), txt_path.to_str().unwrap()).unwrap();
+        assert_eq!(found, vec!["addr1".to_string()]);
+
+        std::fs::remove_file(&db_path).ok();
+        std::fs::remove_file(&txt_path).ok();
+    }
+
+    #[test]
+    fn check_addresses_parallel_preserves_input_order_across_chunks() {
+        let db_path = temp_path("parallel.db");
+        let txt_path = temp_path("parallel.txt");
+        let _ = std::fs::remove_file(&db_path);
+        let _ = std::fs::remove_file(&txt_path);
+        make_test_db(&db_path);
+
+        let mut file = std::fs::File::create(&txt_path).unwrap();
+        writeln!(file, "addr1").unwrap();
+        writeln!(file, "nope").unwrap();
+        writeln!(file, "addr3").unwrap();
+        drop(file);
+
+        let found =
+            check_addresses_parallel(db_path.to_str().unwrap(), txt_path.to_str().unwrap(), 2, false).unwrap();
+        assert_eq!(found, vec!["addr1".to_string(), "addr3".to_string()]);
+
+        std::fs::remove_file(&db_path).ok();
+        std::fs::remove_file(&txt_path).ok();
+    }
+
+    #[test]
+    fn check_addresses_parallel_dedups_duplicate_across_chunk_boundary() {
+        let db_path = temp_path("parallel_dedup.db");
+        let txt_path = temp_path("parallel_dedup.txt");
+        let _ = std::fs::remove_file(&db_path);
+        let _ = std::fs::remove_file(&txt_path);
+        make_test_db(&db_path);
+
+        // 20 copies of the same address with 4 threads guarantees the
+        // duplicate lands in more than one chunk.
+        let mut file = std::fs::File::create(&txt_path).unwrap();
+        for _ in 0..20 {
+            writeln!(file, "addr1").unwrap();
+        }
+        drop(file);
+
+        let found =
+            check_addresses_parallel(db_path.to_str().unwrap(), txt_path.to_str().unwrap(), 4, false).unwrap();
+        assert_eq!(found, vec!["addr1".to_string()]);
+
+        std::fs::remove_file(&db_path).ok();
+        std::fs::remove_file(&txt_path).ok();
+    }
+}
@@ -0,0 +1,118 @@
+use serde::Serialize;
+use std::collections::HashSet;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Format {
+    Text,
+    Json,
+    Ndjson,
+}
+
+impl Format {
+    pub(crate) fn parse(value: &str) -> Option<Format> {
+        match value {
+            "text" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            "ndjson" => Some(Format::Ndjson),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    found: &'a [String],
+    not_found: &'a [String],
+    db: &'a str,
+    input: &'a str,
+}
+
+#[derive(Serialize)]
+struct NdjsonLine<'a> {
+    address: &'a str,
+    found: bool,
+}
+
+// Comparison key for found/not-found membership: under `--normalize` the
+// DB's stored casing/whitespace can differ from the raw input line, so
+// membership has to be decided on normalized forms, not exact strings.
+fn key(value: &str, normalize: bool) -> String {
+    if normalize {
+        crate::normalize_address(value)
+    } else {
+        value.to_string()
+    }
+}
+
+fn not_found(addresses: &[String], found: &[String], normalize: bool) -> Vec<String> {
+    let found_keys: HashSet<String> = found.iter().map(|f| key(f, normalize)).collect();
+    addresses
+        .iter()
+        .filter(|addr| !found_keys.contains(&key(addr, normalize)))
+        .cloned()
+        .collect()
+}
+
+// Writes the structured report to stdout for `Format::Json`/`Format::Ndjson`.
+// `addresses` is the full input list in original order; `found` is the
+// subset the database matched.
+pub(crate) fn write_structured(
+    format: Format,
+    addresses: &[String],
+    found: &[String],
+    db_path: &str,
+    txt_path: &str,
+    normalize: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if format == Format::Text {
+        return Ok(());
+    }
+
+    let not_found = not_found(addresses, found, normalize);
+
+    match format {
+        Format::Text => unreachable!(),
+        Format::Json => {
+            let report = JsonReport { found, not_found: &not_found, db: db_path, input: txt_path };
+            println!("{}", serde_json::to_string(&report)?);
+        }
+        Format::Ndjson => {
+            let not_found_keys: HashSet<String> = not_found.iter().map(|a| key(a, normalize)).collect();
+            for address in addresses {
+                let line = NdjsonLine { address, found: !not_found_keys.contains(&key(address, normalize)) };
+                println!("{}", serde_json::to_string(&line)?);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_parse_accepts_known_values() {
+        assert!(Format::parse("text") == Some(Format::Text));
+        assert!(Format::parse("json") == Some(Format::Json));
+        assert!(Format::parse("ndjson") == Some(Format::Ndjson));
+        assert!(Format::parse("xml").is_none());
+    }
+
+    #[test]
+    fn not_found_is_exact_without_normalize() {
+        let addresses = vec!["Addr1".to_string(), "addr2".to_string()];
+        let found = vec!["addr1".to_string()];
+        let result = not_found(&addresses, &found, false);
+        assert_eq!(result, vec!["Addr1".to_string(), "addr2".to_string()]);
+    }
+
+    #[test]
+    fn not_found_matches_case_and_whitespace_when_normalized() {
+        let addresses = vec![" Addr1 ".to_string(), "addr2".to_string()];
+        let found = vec!["addr1".to_string()];
+        let result = not_found(&addresses, &found, true);
+        assert_eq!(result, vec!["addr2".to_string()]);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{build_pool, check_batch, default_thread_count, DEFAULT_DB_PATH};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+const DEFAULT_PORT: u16 = 8080;
+
+struct AppState {
+    pool: Pool<SqliteConnectionManager>,
+}
+
+#[derive(Serialize)]
+struct CheckResponse {
+    found: Vec<String>,
+    checked: usize,
+}
+
+#[derive(Deserialize)]
+struct PostCheckBody {
+    addresses: Vec<String>,
+}
+
+// Parses `serve [db_path] [--port N] [--threads N]` and runs the HTTP
+// server until interrupted.
+pub(crate) fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let (db_path, port, threads) = parse_serve_args(args);
+
+    let pool = build_pool(&db_path, threads as u32)?;
+    let state = Arc::new(AppState { pool });
+
+    let app = Router::new()
+        .route("/check", get(get_check).post(post_check))
+        .with_state(state);
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async move {
+        let listener = tokio::net::TcpListener::bind(("0.0.0.0", port)).await?;
+        eprintln!("listening on http://{}", listener.local_addr()?);
+        axum::serve(listener, app).await?;
+        Ok::<(), Box<dyn std::error::Error>>(())
+    })
+}
+
+fn parse_serve_args(args: &[String]) -> (String, u16, usize) {
+    let mut db_path = DEFAULT_DB_PATH.to_string();
+    let mut port = DEFAULT_PORT;
+    let mut threads = default_thread_count();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--port" => {
+                if let Some(value) = iter.next() {
+                    port = value.parse().unwrap_or(DEFAULT_PORT);
+                }
+            }
+            "--threads" => {
+                if let Some(value) = iter.next() {
+                    if let Ok(n) = value.parse() {
+                        threads = n;
+                    }
+                }
+            }
+            other => db_path = other.to_string(),
+        }
+    }
+
+    (db_path, port, threads)
+}
+
+async fn get_check(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<CheckResponse>, (StatusCode, String)> {
+    let address = params.get("address").cloned().unwrap_or_default();
+    let checked = usize::from(!address.is_empty());
+    let addresses = if address.is_empty() { Vec::new() } else { vec![address] };
+
+    run_check(state, addresses, checked).await.map(Json)
+}
+
+async fn post_check(
+    State(state): State<Arc<AppState>>,
+    Json(body): Json<PostCheckBody>,
+) -> Result<Json<CheckResponse>, (StatusCode, String)> {
+    let checked = body.addresses.len();
+    run_check(state, body.addresses, checked).await.map(Json)
+}
+
+// A lookup failure (pool exhausted, connection error, ...) must not look
+// like "checked N, found none" to the caller, so errors are surfaced as a
+// 500 instead of collapsing into an empty `CheckResponse`.
+async fn run_check(
+    state: Arc<AppState>,
+    addresses: Vec<String>,
+    checked: usize,
+) -> Result<CheckResponse, (StatusCode, String)> {
+    if addresses.is_empty() {
+        return Ok(CheckResponse { found: Vec::new(), checked });
+    }
+
+    let task_result = tokio::task::spawn_blocking(move || -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let conn = state.pool.get().map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
+        check_batch(&conn, &addresses, false).map_err(|e| e.to_string().into())
+    })
+    .await;
+
+    match task_result {
+        Ok(Ok(found)) => Ok(CheckResponse { found, checked }),
+        Ok(Err(e)) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        Err(join_err) => Err((StatusCode::INTERNAL_SERVER_ERROR, join_err.to_string())),
+    }
+}